@@ -0,0 +1,162 @@
+//! Typed representation of the docker-compose definition that describes the
+//! Tower stack, so image tags, ports and volumes can be overridden from a
+//! file instead of being hardcoded in the installer binary.
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+const DEFAULT_COMPOSE: &str = include_str!("../assets/docker-compose.default.yml");
+
+#[derive(Debug)]
+pub enum ComposeError {
+    Read(std::io::Error),
+    Parse(serde_yaml::Error),
+}
+
+impl fmt::Display for ComposeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ComposeError::Read(err) => write!(f, "failed to read compose file: {}", err),
+            ComposeError::Parse(err) => write!(f, "failed to parse compose file: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ComposeError {}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Environment {
+    List(Vec<String>),
+    Map(HashMap<String, String>),
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Environment::List(Vec::new())
+    }
+}
+
+impl Environment {
+    /// Normalize either compose environment form into `KEY=VALUE` pairs.
+    pub fn to_env_list(&self) -> Vec<String> {
+        match self {
+            Environment::List(list) => list.clone(),
+            Environment::Map(map) => map.iter().map(|(k, v)| format!("{}={}", k, v)).collect(),
+        }
+    }
+}
+
+/// A readiness probe for a service, declared under the non-standard
+/// `x-ready-when` compose extension (the `x-` prefix is the documented way
+/// to attach tool-specific data to a compose file without upsetting other
+/// compose tooling that might also read it).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ReadyCondition {
+    /// The container's reported state is `running` and it isn't restarting.
+    Running,
+    /// A host-published port accepts TCP connections.
+    PortOpen { port: u16 },
+    /// A line in the container's logs matches this regex.
+    LogMatches { pattern: String },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Service {
+    pub image: String,
+    #[serde(default)]
+    pub ports: Vec<String>,
+    #[serde(default)]
+    pub environment: Environment,
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    #[serde(default)]
+    pub volumes: Vec<String>,
+    #[serde(default)]
+    pub restart: Option<String>,
+    #[serde(default, rename = "x-ready-when")]
+    pub ready_when: Vec<ReadyCondition>,
+}
+
+impl Service {
+    /// Parse `"host:container"` port mappings into numeric pairs, skipping
+    /// anything that doesn't fit the pattern.
+    pub fn port_mappings(&self) -> Vec<(u16, u16)> {
+        self.ports
+            .iter()
+            .filter_map(|mapping| {
+                let mut parts = mapping.splitn(2, ':');
+                let host = parts.next()?.parse::<u16>().ok()?;
+                let container = parts.next()?.parse::<u16>().ok()?;
+                Some((host, container))
+            })
+            .collect()
+    }
+
+    pub fn host_ports(&self) -> Vec<u16> {
+        self.port_mappings().into_iter().map(|(host, _)| host).collect()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DockerCompose {
+    pub version: String,
+    pub services: HashMap<String, Service>,
+    #[serde(default)]
+    pub volumes: HashMap<String, Option<serde_yaml::Value>>,
+}
+
+/// Prefix a compose service/volume name with the `tower` project name, the
+/// way `docker-compose -p tower` would.
+pub fn project_name(name: &str) -> String {
+    format!("tower_{}", name)
+}
+
+impl DockerCompose {
+    pub fn load(path: &Path) -> Result<Self, ComposeError> {
+        let raw = fs::read_to_string(path).map_err(ComposeError::Read)?;
+        serde_yaml::from_str(&raw).map_err(ComposeError::Parse)
+    }
+
+    /// The compose definition embedded in the binary, used when the operator
+    /// doesn't point `deploy` at a custom compose file.
+    pub fn embedded_default() -> Self {
+        serde_yaml::from_str(DEFAULT_COMPOSE).expect("embedded docker-compose.yml is malformed")
+    }
+
+    pub fn images(&self) -> Vec<String> {
+        self.services.values().map(|s| s.image.clone()).collect()
+    }
+
+    pub fn host_ports(&self) -> Vec<u16> {
+        self.services
+            .values()
+            .flat_map(|s| s.host_ports())
+            .collect()
+    }
+
+    /// Order services so each one comes after everything it `depends_on`.
+    pub fn start_order(&self) -> Vec<String> {
+        let mut ordered = Vec::with_capacity(self.services.len());
+        let mut remaining: Vec<&String> = self.services.keys().collect();
+        while !remaining.is_empty() {
+            let next_index = remaining.iter().position(|name| {
+                self.services[*name]
+                    .depends_on
+                    .iter()
+                    .all(|dep| ordered.contains(dep))
+            });
+            match next_index {
+                Some(index) => ordered.push(remaining.remove(index).clone()),
+                // A dependency cycle (or a typo'd depends_on); append the rest
+                // in whatever (unspecified) map order is left rather than looping forever.
+                None => ordered.extend(remaining.drain(..).cloned()),
+            }
+        }
+        ordered
+    }
+}
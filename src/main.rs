@@ -1,16 +1,82 @@
 #[macro_use]
 extern crate prettytable;
 
+mod compose;
+mod engine;
+mod readiness;
+mod rollback;
+mod status;
+
+use bollard::container::Config;
+use bollard::models::{HostConfig, PortBinding};
 use byte_unit::{Byte, ByteUnit};
-use clap::{App, Arg, SubCommand};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use compose::{project_name, ComposeError, DockerCompose, Service};
+use engine::{Engine, EngineError};
 use prettytable::{color, Attr, Cell, Row, Table};
+use readiness::ReadinessError;
+use rollback::Rollback;
+use std::collections::HashMap;
 use std::env;
-use std::io::Write;
+use std::fmt;
 use std::net::TcpListener;
-use std::path::PathBuf;
-use std::process::{Command, Stdio};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
 use sysinfo::{DiskExt, SystemExt};
 
+const TOWER_NETWORK: &str = "tower_default";
+
+/// Errors that can abort a CLI run with a clean message instead of a panic.
+#[derive(Debug)]
+enum CliError {
+    Engine(EngineError),
+    Compose(ComposeError),
+    Readiness(ReadinessError),
+    Io(std::io::Error),
+    /// A precondition we checked ourselves (hardware requirements, a build
+    /// step that exited non-zero), not a daemon/filesystem error.
+    Message(String),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CliError::Engine(err) => write!(f, "{}", err),
+            CliError::Compose(err) => write!(f, "{}", err),
+            CliError::Readiness(err) => write!(f, "{}", err),
+            CliError::Io(err) => write!(f, "{}", err),
+            CliError::Message(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+impl From<EngineError> for CliError {
+    fn from(err: EngineError) -> Self {
+        CliError::Engine(err)
+    }
+}
+
+impl From<ComposeError> for CliError {
+    fn from(err: ComposeError) -> Self {
+        CliError::Compose(err)
+    }
+}
+
+impl From<ReadinessError> for CliError {
+    fn from(err: ReadinessError) -> Self {
+        CliError::Readiness(err)
+    }
+}
+
+impl From<std::io::Error> for CliError {
+    fn from(err: std::io::Error) -> Self {
+        CliError::Io(err)
+    }
+}
+
 struct HardwareRequirement {
     cpu_cores: u8,
     memory: Byte,
@@ -35,13 +101,16 @@ fn is_port_available(port: &u16) -> bool {
     }
 }
 
-fn check_hardware_requirement(force: &bool) {
+async fn check_hardware_requirement(
+    force: &bool,
+    compose: &DockerCompose,
+    engine: &Engine,
+) -> Result<(), CliError> {
     let required_requirement = HardwareRequirement {
         cpu_cores: 2,
         memory: Byte::from_unit(4.0, ByteUnit::GiB).unwrap(),
         storage_space: Byte::from_unit(40.0, ByteUnit::GiB).unwrap(),
-        // TODO: check 80 8800
-        ports: vec![8811],
+        ports: compose.host_ports(),
     };
     let expeceted_requirement = HardwareRequirement {
         cpu_cores: 4,
@@ -50,13 +119,34 @@ fn check_hardware_requirement(force: &bool) {
         ports: vec![],
     };
 
-    let mut system = sysinfo::System::new_all();
-    system.refresh_all();
+    // When deploying to a remote daemon, report what it has instead of what
+    // this machine has.
+    let (actual_cpu_cores, actual_memory, remained_storage_space) = if engine.is_remote() {
+        let resources = engine.daemon_resources().await?;
+        (
+            resources.cpu_cores as u8,
+            Byte::from_bytes(resources.memory_bytes as u128),
+            None,
+        )
+    } else {
+        let mut system = sysinfo::System::new_all();
+        system.refresh_all();
+        let storage = Byte::from_bytes(
+            system
+                .get_disks()
+                .iter()
+                .fold(0, |sum, disk| sum + disk.get_available_space() as u128),
+        );
+        (
+            system.get_processors().len() as u8,
+            Byte::from_unit(system.get_total_memory() as f64, ByteUnit::KiB).unwrap(),
+            Some(storage),
+        )
+    };
 
     let mut table = Table::new();
     table.add_row(row!["", "Required", "Expected", "Actual"]);
 
-    let actual_cpu_cores = system.get_processors().len() as u8;
     table.add_row(Row::new(vec![
         Cell::new("cpu cores"),
         Cell::new(&required_requirement.cpu_cores.to_string()),
@@ -70,7 +160,6 @@ fn check_hardware_requirement(force: &bool) {
             .with_style(Attr::Bold),
     ]));
 
-    let actual_memory = Byte::from_unit(system.get_total_memory() as f64, ByteUnit::KiB).unwrap();
     table.add_row(Row::new(vec![
         Cell::new("memory"),
         Cell::new(
@@ -94,12 +183,18 @@ fn check_hardware_requirement(force: &bool) {
             .with_style(Attr::Bold),
     ]));
 
-    let remained_storage_space = Byte::from_bytes(
-        system
-            .get_disks()
-            .iter()
-            .fold(0, |sum, disk| sum + disk.get_available_space() as u128),
-    );
+    let actual_storage_cell = match remained_storage_space {
+        Some(space) => Cell::new(&space.get_appropriate_unit(true).to_string())
+            .with_style(Attr::ForegroundColor(get_color(
+                space.get_bytes() as u64,
+                required_requirement.storage_space.get_bytes() as u64,
+                expeceted_requirement.storage_space.get_bytes() as u64,
+            )))
+            .with_style(Attr::Bold),
+        // The Docker `info` endpoint doesn't report free disk space, so we
+        // can't check this for a remote daemon.
+        None => Cell::new("n/a"),
+    };
     table.add_row(Row::new(vec![
         Cell::new("storage space"),
         Cell::new(
@@ -114,17 +209,7 @@ fn check_hardware_requirement(force: &bool) {
                 .get_appropriate_unit(true)
                 .to_string(),
         ),
-        Cell::new(
-            &remained_storage_space
-                .get_appropriate_unit(true)
-                .to_string(),
-        )
-        .with_style(Attr::ForegroundColor(get_color(
-            remained_storage_space.get_bytes() as u64,
-            required_requirement.storage_space.get_bytes() as u64,
-            expeceted_requirement.storage_space.get_bytes() as u64,
-        )))
-        .with_style(Attr::Bold),
+        actual_storage_cell,
     ]));
 
     let unavailable_ports = &required_requirement
@@ -163,105 +248,74 @@ fn check_hardware_requirement(force: &bool) {
     table.printstd();
 
     if *force {
-        return;
+        return Ok(());
     }
 
     if actual_cpu_cores < required_requirement.cpu_cores {
-        panic!("CPU cores not enough.");
+        return Err(CliError::Message("CPU cores not enough.".to_owned()));
     }
     if actual_memory < required_requirement.memory {
-        panic!("Memory not enough.")
+        return Err(CliError::Message("Memory not enough.".to_owned()));
     }
-    if remained_storage_space < required_requirement.storage_space {
-        panic!("Storage space not enough.");
+    if let Some(space) = remained_storage_space {
+        if space < required_requirement.storage_space {
+            return Err(CliError::Message("Storage space not enough.".to_owned()));
+        }
     }
     if unavailable_ports.len() > 0 {
-        panic!("Some ports are not available.");
+        return Err(CliError::Message("Some ports are not available.".to_owned()));
     }
+    Ok(())
 }
 
-fn check_docker() {
-    let docker_status = if cfg!(target_os = "windows") {
-        Command::new("cmd")
-            .args(&["/C", "docker info"])
-            .stdout(Stdio::null())
-            .status()
-            .expect("docker is not running")
-            .success()
-    } else {
-        Command::new("sh")
-            .arg("-c")
-            .arg("docker info")
-            .stdout(Stdio::null())
-            .status()
-            .expect("docker is not running")
-            .success()
-    };
-    if !docker_status {
-        panic!("docker is not running")
-    } else {
-        println!("docker is running")
-    }
-    let docker_compose_status = if cfg!(target_os = "windows") {
-        Command::new("cmd")
-            .args(&["/C", "docker-compose version"])
-            .stdout(Stdio::null())
-            .status()
-    } else {
-        Command::new("sh")
-            .arg("-c")
-            .arg("docker-compose version")
-            .stdout(Stdio::null())
-            .status()
-    };
-    if !docker_compose_status
-        .expect("docker-compose was not installed")
-        .success()
-    {
-        panic!("docker-compose was not installed")
-    } else {
-        println!("docker-compose was installed")
-    }
+async fn check_docker(engine: &Engine) -> Result<(), CliError> {
+    engine.ping().await?;
+    println!("docker is running");
+    Ok(())
 }
 
-fn start_from_source(source_dir: &PathBuf, force: &bool) {
-    let docker_compose_file = source_dir
-        .join("packages/server/docker-compose.yml")
-        .to_str()
-        .expect("failed to get docker-compose.yml")
-        .to_owned();
-    let docker_compose_arg = format!("docker-compose -p tower -f {} up -d", &docker_compose_file);
-    let containers_command = if cfg!(target_os = "windows") {
-        Command::new("cmd")
-            .args(&["/C", &docker_compose_arg])
-            .status()
-    } else {
-        Command::new("sh")
-            .arg("-c")
-            .arg(&docker_compose_arg)
-            .status()
-    };
-    if !containers_command
-        .expect("failed to start tower containers")
-        .success()
-    {
-        panic!("failed to start tower containers")
-    }
+async fn start_from_source(
+    engine: &Engine,
+    source_dir: &PathBuf,
+    force: &bool,
+    rollback: &Rollback,
+) -> Result<(), CliError> {
+    // Deploy the source checkout's own compose definition, not the
+    // packaged/`--compose-file` one used for the preflight checks: a source
+    // tree can carry service/image/bind overrides that only it knows about.
+    let compose_path = source_dir.join("packages/server/docker-compose.yml");
+    let compose = DockerCompose::load(&compose_path)?;
+    let base_dir = compose_path
+        .parent()
+        .ok_or_else(|| {
+            CliError::Message(format!(
+                "compose file path {} has no parent directory",
+                compose_path.display()
+            ))
+        })?
+        .to_path_buf();
+    start_containers(engine, &compose, false, rollback, Some(&base_dir)).await?;
     let source_dir_str = source_dir.to_str().unwrap();
     let yarn_arg = format!(
         "cd {} && yarn && yarn lerna run prepublish",
         &source_dir_str
     );
-    let yarn_command = if cfg!(target_os = "windows") {
-        Command::new("cmd").args(&["/C", &yarn_arg]).status()
-    } else {
-        Command::new("sh").arg("-c").arg(&yarn_arg).status()
-    };
-    if !yarn_command
-        .expect("failed to build tower from source code")
-        .success()
-    {
-        panic!("failed to build tower from source code")
+    // Run on a blocking-pool thread: this is a long-running build step, and
+    // running it directly on the async executor would starve the Ctrl-C
+    // watcher task on a single-core runner.
+    let yarn_command = tokio::task::spawn_blocking(move || {
+        if cfg!(target_os = "windows") {
+            Command::new("cmd").args(&["/C", &yarn_arg]).status()
+        } else {
+            Command::new("sh").arg("-c").arg(&yarn_arg).status()
+        }
+    })
+    .await
+    .map_err(|err| CliError::Message(format!("tower build task panicked: {}", err)))??;
+    if !yarn_command.success() {
+        return Err(CliError::Message(
+            "failed to build tower from source code".to_owned(),
+        ));
     }
 
     // cd packages/server && yarn prisma deploy
@@ -289,177 +343,228 @@ fn start_from_source(source_dir: &PathBuf, force: &bool) {
         &setup_script,
     ]
     .join(" ");
-    let setup_command = if cfg!(target_os = "windows") {
-        Command::new("cmd")
-            .env("PRISMA_PORT", "8811")
-            .arg("/C")
-            .arg(setup_args)
-            .status()
-    } else {
-        Command::new("sh")
-            .env("PRISMA_PORT", "8811")
-            .arg("-c")
-            .arg(setup_args)
-            .status()
-    };
-    if !setup_command.expect("failed to run setup script").success() {
-        panic!("failed to run setup script")
-    }
-}
-
-fn check_images() -> bool {
-    let images = [
-        "tower:0.2.3",
-        "prismagraphql/prisma:1.34",
-        "postgres:10.3",
-        "openresty/openresty:alpine",
-    ];
-    for image in images.iter() {
-        let command = format!("docker inspect --type=image {}", &image);
-        let status = if cfg!(target_os = "windows") {
+    let setup_command = tokio::task::spawn_blocking(move || {
+        if cfg!(target_os = "windows") {
             Command::new("cmd")
-                .args(&["/C", &command])
-                .stdout(Stdio::null())
-                .stderr(Stdio::null())
+                .env("PRISMA_PORT", "8811")
+                .arg("/C")
+                .arg(setup_args)
                 .status()
         } else {
             Command::new("sh")
+                .env("PRISMA_PORT", "8811")
                 .arg("-c")
-                .arg(&command)
-                .stdout(Stdio::null())
-                .stderr(Stdio::null())
+                .arg(setup_args)
                 .status()
-        };
-        let image_exists = match status {
-            Ok(s) => s.success(),
-            Err(_) => false,
-        };
+        }
+    })
+    .await
+    .map_err(|err| CliError::Message(format!("setup script task panicked: {}", err)))??;
+    if !setup_command.success() {
+        return Err(CliError::Message("failed to run setup script".to_owned()));
+    }
+    Ok(())
+}
+
+async fn check_images(engine: &Engine, compose: &DockerCompose) -> Result<bool, CliError> {
+    for image in compose.images() {
+        let image_exists = engine.image_exists(&image).await?;
         if !image_exists {
-            println!("{} image is missing", &image);
-            return false;
+            println!("{} image is missing", image);
+            return Ok(false);
         }
     }
     println!("all image exists");
-    true
+    Ok(true)
 }
 
-fn load_images(tar_dir: &PathBuf) {
-    let docker_arg = format!("docker load --input {}", tar_dir.to_str().unwrap());
-    let command = if cfg!(target_os = "windows") {
-        Command::new("cmd").args(&["/C", &docker_arg]).status()
-    } else {
-        Command::new("sh").arg("-c").arg(&docker_arg).status()
+async fn load_images(engine: &Engine, tar_dir: &PathBuf) -> Result<(), CliError> {
+    engine.load_image_archive(tar_dir).await?;
+    Ok(())
+}
+
+/// Translate a compose `Service` into a bollard container config, rewriting
+/// named-volume sources to their project-prefixed equivalents and resolving
+/// bind-mount sources to absolute host paths (the Docker Engine API, unlike
+/// `docker-compose`, doesn't resolve binds relative to the compose file for
+/// us). `base_dir` is the directory relative binds are resolved against.
+fn container_config(compose: &DockerCompose, service: &Service, base_dir: Option<&Path>) -> Config<String> {
+    let mut port_bindings = HashMap::new();
+    for (host, container) in service.port_mappings() {
+        port_bindings.insert(
+            format!("{}/tcp", container),
+            Some(vec![PortBinding {
+                host_ip: Some("0.0.0.0".to_owned()),
+                host_port: Some(host.to_string()),
+            }]),
+        );
+    }
+
+    let binds = service
+        .volumes
+        .iter()
+        .map(|bind| match bind.split_once(':') {
+            Some((source, target)) if compose.volumes.contains_key(source) => {
+                format!("{}:{}", project_name(source), target)
+            }
+            Some((source, target)) if Path::new(source).is_absolute() => {
+                format!("{}:{}", source, target)
+            }
+            Some((source, target)) => {
+                let base_dir = base_dir.unwrap_or_else(|| {
+                    panic!(
+                        "bind mount \"{}\" is a relative path but no compose file or source \
+                         directory was given to resolve it against",
+                        bind
+                    )
+                });
+                let resolved = base_dir.join(source);
+                format!(
+                    "{}:{}",
+                    resolved.to_str().expect("bind mount path is not valid UTF-8"),
+                    target
+                )
+            }
+            None => bind.clone(),
+        })
+        .collect();
+
+    let exposed_ports = service
+        .port_mappings()
+        .into_iter()
+        .map(|(_, container)| (format!("{}/tcp", container), HashMap::new()))
+        .collect();
+
+    let restart_policy_name = match service.restart.as_deref() {
+        Some("always") => bollard::models::RestartPolicyNameEnum::ALWAYS,
+        Some("on-failure") => bollard::models::RestartPolicyNameEnum::ON_FAILURE,
+        Some("unless-stopped") => bollard::models::RestartPolicyNameEnum::UNLESS_STOPPED,
+        _ => bollard::models::RestartPolicyNameEnum::NO,
     };
-    if !command.expect("failed to load docker images").success() {
-        panic!("failed to load docker images")
+
+    Config {
+        image: Some(service.image.clone()),
+        env: Some(service.environment.to_env_list()),
+        exposed_ports: Some(exposed_ports),
+        host_config: Some(HostConfig {
+            network_mode: Some(TOWER_NETWORK.to_owned()),
+            restart_policy: Some(bollard::models::RestartPolicy {
+                name: Some(restart_policy_name),
+                ..Default::default()
+            }),
+            port_bindings: Some(port_bindings),
+            binds: Some(binds),
+            ..Default::default()
+        }),
+        ..Default::default()
     }
 }
 
-fn start_from_image() {
-    let docker_compose_arg = "docker-compose -p tower -f - up -d";
-    let mut child = if cfg!(target_os = "windows") {
-        Command::new("cmd")
-            .args(&["/C", &docker_compose_arg])
-            .stdin(Stdio::piped())
-            .spawn()
-            .expect("failed to start tower containers")
-    } else {
-        Command::new("sh")
-            .arg("-c")
-            .arg(&docker_compose_arg)
-            .stdin(Stdio::piped())
-            .spawn()
-            .expect("failed to start tower containers")
-    };
-    {
-        let child_stdin = child.stdin.as_mut().expect("failed to get stdin handler");
-        child_stdin
-            .write_all(
-                b"
-version: '3'
-services:
-  prisma:
-    image: prismagraphql/prisma:1.34
-    restart: always
-    depends_on:
-      - 'postgres'
-    ports:
-      - '8811:8811'
-    environment:
-      PRISMA_CONFIG: |
-        port: 8811
-        databases:
-          default:
-            connector: postgres
-            host: postgres
-            port: 5432
-            user: prisma
-            password: prisma
-            rawAccess: true
-  postgres:
-    image: postgres:10.3
-    restart: always
-    environment:
-      POSTGRES_USER: prisma
-      POSTGRES_PASSWORD: prisma
-    volumes:
-      - postgres:/var/lib/postgresql/data
-  openresty:
-    image: openresty/openresty:alpine
-    restart: always
-    ports:
-      - '80:80'
-    environment:
-      - NGINX_PORT=80
-    volumes:
-      - ../server/config/nginx:/etc/nginx/conf.d
-      - ../ui/build:/www/tower
-  server:
-    image: tower:0.2.3
-    restart: always
-    depends_on:
-      - 'prisma'
-    ports:
-      - '8800:8800'
-volumes:
-  postgres: ~
-",
-            )
-            .expect("failed to start tower containers");
+async fn ensure_stack_resources(
+    engine: &Engine,
+    compose: &DockerCompose,
+    rollback: &Rollback,
+) -> Result<(), CliError> {
+    let network_existed = engine.list_network_names().await.unwrap_or_default().contains(&TOWER_NETWORK.to_owned());
+    engine.ensure_network(TOWER_NETWORK).await?;
+    if !network_existed {
+        rollback.track_network(TOWER_NETWORK).await;
     }
-    if !child
-        .wait()
-        .expect("failed to start tower containers")
-        .success()
-    {
-        panic!("failed to start tower containers")
+    for volume in compose.volumes.keys() {
+        let volume_name = project_name(volume);
+        let volume_existed = engine
+            .list_volume_names()
+            .await
+            .unwrap_or_default()
+            .contains(&volume_name);
+        engine.ensure_volume(&volume_name).await?;
+        if !volume_existed {
+            rollback.track_volume(volume_name).await;
+        }
     }
+    Ok(())
 }
 
-fn shut_down() {
-    let docker_compose_arg = "docker-compose -p tower down";
-    let containers_command = if cfg!(target_os = "windows") {
-        Command::new("cmd")
-            .args(&["/C", &docker_compose_arg])
-            .status()
-    } else {
-        Command::new("sh")
-            .arg("-c")
-            .arg(&docker_compose_arg)
-            .status()
-    };
-    if !containers_command
-        .expect("failed to shut down tower containers")
-        .success()
-    {
-        panic!("failed to shut down tower containers")
+async fn start_containers(
+    engine: &Engine,
+    compose: &DockerCompose,
+    pull: bool,
+    rollback: &Rollback,
+    base_dir: Option<&Path>,
+) -> Result<(), CliError> {
+    ensure_stack_resources(engine, compose, rollback).await?;
+    for name in compose.start_order() {
+        let service = &compose.services[&name];
+        if pull {
+            engine.ensure_image(&service.image).await?;
+        }
+        let container_name = project_name(&name);
+        engine
+            .create_and_start_container(&container_name, container_config(compose, service, base_dir))
+            .await?;
+        rollback.track_container(container_name).await;
     }
+
+    println!("> waiting for services to become ready...");
+    let probes: Vec<(String, Vec<compose::ReadyCondition>)> = compose
+        .services
+        .iter()
+        .map(|(name, service)| (project_name(name), service.ready_when.clone()))
+        .collect();
+    readiness::wait_until_ready(engine, &probes).await?;
+    Ok(())
+}
+
+async fn start_from_image(
+    engine: &Engine,
+    compose: &DockerCompose,
+    rollback: &Rollback,
+    base_dir: Option<&Path>,
+) -> Result<(), CliError> {
+    start_containers(engine, compose, true, rollback, base_dir).await
 }
 
-fn main() {
+async fn shut_down(engine: &Engine, compose: &DockerCompose) -> Result<(), CliError> {
+    for name in compose.services.keys() {
+        engine.remove_container(&project_name(name)).await?;
+    }
+    engine.remove_network(TOWER_NETWORK).await?;
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() {
+    let compose_file_arg = Arg::with_name("compose_file")
+        .long("compose-file")
+        .help("Path to a docker-compose.yml describing the tower stack. Defaults to the bundled definition.")
+        .takes_value(true);
+
     let matches = App::new("Tower Installer")
         .version("0.1.0")
-        .subcommand(SubCommand::with_name("down").about("Shut down tower serivce."))
+        .arg(
+            Arg::with_name("docker_host")
+                .long("docker-host")
+                .help("Docker engine endpoint to deploy to: a unix socket path (unix://...) or tcp://host:2376. Defaults to DOCKER_HOST, then the local daemon.")
+                .takes_value(true)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("docker_cert_path")
+                .long("docker-cert-path")
+                .help("Directory holding key.pem/cert.pem/ca.pem for a TLS-secured tcp:// docker-host. Defaults to DOCKER_CERT_PATH.")
+                .takes_value(true)
+                .global(true),
+        )
+        .subcommand(
+            SubCommand::with_name("down")
+                .about("Shut down tower serivce.")
+                .arg(compose_file_arg.clone()),
+        )
+        .subcommand(
+            SubCommand::with_name("status")
+                .about("Report existing containers, volumes and networks that a deploy would reuse or conflict with.")
+                .arg(compose_file_arg.clone()),
+        )
         .subcommand(
             SubCommand::with_name("deploy").about("Deploy tower service")
                 .arg(
@@ -475,47 +580,127 @@ fn main() {
                         .takes_value(true)
                 )
                 .arg(Arg::with_name("force").long("force").help("Reset data and force deploy a new tower service")
-            )
+                )
+                .arg(compose_file_arg)
         )
         .get_matches();
 
-    match matches.subcommand() {
-        ("deploy", Some(sub_matches)) => {
-            let force = &sub_matches.is_present("force");
-            println!("> checking hardware requirements...");
-            check_hardware_requirement(force);
-            println!("> checking docker and docker-compose...");
-            check_docker();
-            check_images();
-            println!("> starting tower containers...");
-            if let Some(source_dir_value) = sub_matches.value_of("source_dir") {
-                let mut source_dir = PathBuf::from(source_dir_value);
-                if !source_dir.is_absolute() {
-                    source_dir = env::current_dir()
-                        .expect("failed to get current directory")
-                        .join(source_dir)
-                        .canonicalize()
-                        .expect("failed to canoicalize source directory");
-                }
-                start_from_source(&source_dir, force);
-                return;
-            }
-            if let Some(tar_dir_value) = sub_matches.value_of("tar_dir") {
-                let mut tar_dir = PathBuf::from(tar_dir_value);
-                if !tar_dir.is_absolute() {
-                    tar_dir = env::current_dir()
-                        .expect("failed to get current directory")
-                        .join(tar_dir)
-                        .canonicalize()
-                        .expect("failed to canoicalize source directory");
-                    load_images(&tar_dir)
-                }
-            }
-            start_from_image()
+    let engine = match Engine::connect(
+        matches.value_of("docker_host"),
+        matches.value_of("docker_cert_path").map(Path::new),
+    ) {
+        Ok(engine) => Arc::new(engine),
+        Err(err) => {
+            eprintln!("error: failed to connect to docker engine: {}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let result = match matches.subcommand() {
+        ("deploy", Some(sub_matches)) => run_deploy(&engine, sub_matches).await,
+        ("down", Some(sub_matches)) => run_down(&engine, sub_matches).await,
+        ("status", Some(sub_matches)) => run_status(&engine, sub_matches).await,
+        _ => Ok(()),
+    };
+
+    if let Err(err) = result {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+async fn run_deploy(engine: &Arc<Engine>, sub_matches: &ArgMatches<'_>) -> Result<(), CliError> {
+    let force = &sub_matches.is_present("force");
+    let compose = load_compose(sub_matches.value_of("compose_file"))?;
+    println!("> checking hardware requirements...");
+    check_hardware_requirement(force, &compose, engine).await?;
+    println!("> checking docker...");
+    check_docker(engine).await?;
+    check_images(engine, &compose).await?;
+    println!("> checking for pre-existing tower resources...");
+    let findings = status::collect(engine, &compose).await;
+    status::print_report(&findings);
+
+    let rollback = Rollback::new();
+    rollback::watch_for_interrupt(engine.clone(), rollback.clone());
+
+    println!("> starting tower containers...");
+    // Unwind anything tracked in `rollback` on an ordinary failure too, not
+    // just on Ctrl-C: a failed pull, container-create or readiness timeout
+    // is far more common than an interrupt, and should leave the daemon just
+    // as re-runnable.
+    match deploy_containers(engine, &compose, sub_matches, force, &rollback).await {
+        Ok(()) => {
+            rollback.clear().await;
+            Ok(())
         }
-        ("down", Some(_)) => {
-            shut_down();
+        Err(err) => {
+            eprintln!(
+                "> deploy failed ({}), rolling back partially created tower resources...",
+                err
+            );
+            rollback.unwind(engine).await;
+            Err(err)
         }
-        _ => {}
+    }
+}
+
+/// The part of `deploy` that actually creates resources, separated out so
+/// `run_deploy` can roll everything in `rollback` back on any failure here.
+async fn deploy_containers(
+    engine: &Engine,
+    compose: &DockerCompose,
+    sub_matches: &ArgMatches<'_>,
+    force: &bool,
+    rollback: &Rollback,
+) -> Result<(), CliError> {
+    if let Some(source_dir_value) = sub_matches.value_of("source_dir") {
+        let mut source_dir = PathBuf::from(source_dir_value);
+        if !source_dir.is_absolute() {
+            source_dir = env::current_dir()?.join(source_dir).canonicalize()?;
+        }
+        return start_from_source(engine, &source_dir, force, rollback).await;
+    }
+    if let Some(tar_dir_value) = sub_matches.value_of("tar_dir") {
+        let mut tar_dir = PathBuf::from(tar_dir_value);
+        if !tar_dir.is_absolute() {
+            tar_dir = env::current_dir()?.join(tar_dir).canonicalize()?;
+            load_images(engine, &tar_dir).await?;
+        }
+    }
+    let base_dir = match sub_matches.value_of("compose_file") {
+        Some(path) => {
+            let dir = env::current_dir()?
+                .join(path)
+                .parent()
+                .ok_or_else(|| {
+                    CliError::Message(format!("compose file path {} has no parent directory", path))
+                })?
+                .to_path_buf();
+            Some(dir)
+        }
+        None => None,
     };
+    start_from_image(engine, compose, rollback, base_dir.as_deref()).await
+}
+
+async fn run_down(engine: &Engine, sub_matches: &ArgMatches<'_>) -> Result<(), CliError> {
+    let compose = load_compose(sub_matches.value_of("compose_file"))?;
+    shut_down(engine, &compose).await
+}
+
+async fn run_status(engine: &Engine, sub_matches: &ArgMatches<'_>) -> Result<(), CliError> {
+    let compose = load_compose(sub_matches.value_of("compose_file"))?;
+    let findings = status::collect(engine, &compose).await;
+    status::print_report(&findings);
+    Ok(())
+}
+
+/// Load the compose definition from `--compose-file`, falling back to the
+/// one bundled with the installer.
+fn load_compose(compose_file: Option<&str>) -> Result<DockerCompose, CliError> {
+    match compose_file {
+        Some(path) => Ok(DockerCompose::load(&PathBuf::from(path))?),
+        None => Ok(DockerCompose::embedded_default()),
+    }
 }
@@ -0,0 +1,85 @@
+//! Waits for services to become ready after they're started, so dependent
+//! steps (like running Prisma migrations against postgres) don't race a
+//! container that's still booting.
+
+use crate::compose::ReadyCondition;
+use crate::engine::Engine;
+use regex::Regex;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+const TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Debug)]
+pub struct ReadinessError {
+    pub never_ready: Vec<String>,
+}
+
+impl fmt::Display for ReadinessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "these services never became ready: {}",
+            self.never_ready.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for ReadinessError {}
+
+/// Poll `services` until every one of their `ready_when` conditions is
+/// satisfied, or give up after `TIMEOUT` and report whichever are still not
+/// ready. A service with no conditions is considered ready as soon as it's
+/// running.
+pub async fn wait_until_ready(
+    engine: &Engine,
+    services: &[(String, Vec<ReadyCondition>)],
+) -> Result<(), ReadinessError> {
+    let start = Instant::now();
+    let mut pending: Vec<&(String, Vec<ReadyCondition>)> = services.iter().collect();
+
+    loop {
+        let mut still_pending = Vec::new();
+        for entry in pending {
+            if !is_ready(engine, &entry.0, &entry.1).await {
+                still_pending.push(entry);
+            }
+        }
+        pending = still_pending;
+
+        if pending.is_empty() {
+            return Ok(());
+        }
+        if start.elapsed() >= TIMEOUT {
+            return Err(ReadinessError {
+                never_ready: pending.into_iter().map(|(name, _)| name.clone()).collect(),
+            });
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+async fn is_ready(engine: &Engine, name: &str, conditions: &[ReadyCondition]) -> bool {
+    if conditions.is_empty() {
+        return engine.is_container_running(name).await.unwrap_or(false);
+    }
+    for condition in conditions {
+        let satisfied = match condition {
+            ReadyCondition::Running => engine.is_container_running(name).await.unwrap_or(false),
+            ReadyCondition::PortOpen { port } => engine.port_reachable(*port).await,
+            ReadyCondition::LogMatches { pattern } => match Regex::new(pattern) {
+                Ok(re) => engine
+                    .recent_logs(name)
+                    .await
+                    .map(|logs| re.is_match(&logs))
+                    .unwrap_or(false),
+                Err(_) => false,
+            },
+        };
+        if !satisfied {
+            return false;
+        }
+    }
+    true
+}
@@ -0,0 +1,98 @@
+//! Tracks resources created during a single invocation so an interrupted
+//! deploy can be unwound instead of leaving half a stack running.
+
+use crate::engine::Engine;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+#[derive(Clone)]
+enum Resource {
+    Container(String),
+    Network(String),
+    Volume(String),
+}
+
+fn describe(resource: &Resource) -> String {
+    match resource {
+        Resource::Container(name) => format!("container {}", name),
+        Resource::Network(name) => format!("network {}", name),
+        Resource::Volume(name) => format!("volume {}", name),
+    }
+}
+
+/// A rollback registry: each successful create pushes a cleanup entry here;
+/// `clear` drops them all once a deploy finishes normally, `unwind` tears
+/// them down in reverse order when it doesn't.
+#[derive(Clone)]
+pub struct Rollback {
+    created: Arc<Mutex<Vec<Resource>>>,
+}
+
+impl Rollback {
+    pub fn new() -> Self {
+        Rollback {
+            created: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    pub async fn track_container(&self, name: impl Into<String>) {
+        self.created.lock().await.push(Resource::Container(name.into()));
+    }
+
+    pub async fn track_network(&self, name: impl Into<String>) {
+        self.created.lock().await.push(Resource::Network(name.into()));
+    }
+
+    pub async fn track_volume(&self, name: impl Into<String>) {
+        self.created.lock().await.push(Resource::Volume(name.into()));
+    }
+
+    /// Forget everything tracked so far. Call this once a deploy completes
+    /// successfully so a later Ctrl-C doesn't undo a finished install.
+    pub async fn clear(&self) {
+        self.created.lock().await.clear();
+    }
+
+    /// Tear down every tracked resource, most-recently-created first.
+    pub async fn unwind(&self, engine: &Engine) {
+        let mut created = self.created.lock().await;
+        while let Some(resource) = created.pop() {
+            let result = match &resource {
+                Resource::Container(name) => engine.remove_container(name).await,
+                Resource::Network(name) => engine.remove_network(name).await,
+                Resource::Volume(name) => engine.remove_volume(name).await,
+            };
+            if let Err(err) = result {
+                eprintln!("failed to roll back {}: {}", describe(&resource), err);
+            }
+        }
+    }
+}
+
+/// Spawn a task that unwinds `rollback` and exits non-zero as soon as the
+/// process receives Ctrl-C (SIGINT, or SIGTERM/the Windows equivalent).
+pub fn watch_for_interrupt(engine: Arc<Engine>, rollback: Rollback) {
+    tokio::spawn(async move {
+        wait_for_interrupt().await;
+        eprintln!("\n> interrupted, rolling back partially created tower resources...");
+        rollback.unwind(&engine).await;
+        std::process::exit(1);
+    });
+}
+
+#[cfg(unix)]
+async fn wait_for_interrupt() {
+    use tokio::signal::unix::{signal, SignalKind};
+    let mut sigterm = signal(SignalKind::terminate()).expect("failed to install SIGTERM handler");
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = sigterm.recv() => {}
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_interrupt() {
+    tokio::signal::ctrl_c()
+        .await
+        .expect("failed to install Ctrl-C handler");
+}
@@ -0,0 +1,139 @@
+//! `status` preflight: reports anything already on the daemon that a fresh
+//! Tower deploy would reuse or collide with.
+
+use crate::compose::{project_name, DockerCompose};
+use crate::engine::Engine;
+use crate::TOWER_NETWORK;
+use prettytable::{color, Attr, Cell, Row, Table};
+
+pub enum Severity {
+    /// Not a blocker: already here and will simply be reused (e.g. a named
+    /// volume keeping its data across a redeploy), or a required image that
+    /// is missing locally and will be pulled.
+    Reused,
+    /// Something else is already using a resource we need (e.g. a foreign
+    /// container bound to a port Tower wants).
+    Conflict,
+}
+
+pub struct Finding {
+    pub resource: String,
+    pub detail: String,
+    pub severity: Severity,
+}
+
+/// Query the daemon for anything that would collide with a fresh Tower
+/// install: our own leftover containers/volumes/network, and any other
+/// container already bound to a port we need.
+pub async fn collect(engine: &Engine, compose: &DockerCompose) -> Vec<Finding> {
+    let mut findings = Vec::new();
+
+    let containers = engine.list_all_containers().await.unwrap_or_default();
+    let container_names: Vec<String> = containers
+        .iter()
+        .flat_map(|c| c.names.clone().unwrap_or_default())
+        .map(|n| n.trim_start_matches('/').to_owned())
+        .collect();
+
+    for name in compose.services.keys() {
+        let container_name = project_name(name);
+        if container_names.contains(&container_name) {
+            findings.push(Finding {
+                resource: format!("container {}", container_name),
+                detail: "already exists, redeploy will fail with a name conflict unless removed"
+                    .to_owned(),
+                severity: Severity::Conflict,
+            });
+        }
+    }
+
+    let volume_names = engine.list_volume_names().await.unwrap_or_default();
+    for volume in compose.volumes.keys() {
+        let volume_name = project_name(volume);
+        if volume_names.contains(&volume_name) {
+            findings.push(Finding {
+                resource: format!("volume {}", volume_name),
+                detail: "existing data will survive and be reused, pass --force to reset"
+                    .to_owned(),
+                severity: Severity::Reused,
+            });
+        }
+    }
+
+    let network_names = engine.list_network_names().await.unwrap_or_default();
+    if network_names.contains(&TOWER_NETWORK.to_owned()) {
+        findings.push(Finding {
+            resource: format!("network {}", TOWER_NETWORK),
+            detail: "already exists, will be reused".to_owned(),
+            severity: Severity::Reused,
+        });
+    }
+
+    let our_container_names: Vec<String> =
+        compose.services.keys().map(|n| project_name(n)).collect();
+    for required_port in compose.host_ports() {
+        let bound_by_foreign_container = containers.iter().find(|c| {
+            let is_ours = c
+                .names
+                .clone()
+                .unwrap_or_default()
+                .iter()
+                .any(|n| our_container_names.contains(&n.trim_start_matches('/').to_owned()));
+            !is_ours
+                && c.ports.clone().unwrap_or_default().iter().any(|p| {
+                    p.public_port == Some(required_port as i64)
+                })
+        });
+        if let Some(container) = bound_by_foreign_container {
+            let name = container
+                .names
+                .clone()
+                .unwrap_or_default()
+                .into_iter()
+                .next()
+                .unwrap_or_else(|| container.id.clone().unwrap_or_default());
+            findings.push(Finding {
+                resource: format!("port {}", required_port),
+                detail: format!("already bound by {}", name.trim_start_matches('/')),
+                severity: Severity::Conflict,
+            });
+        }
+    }
+
+    for image in compose.images() {
+        if !engine.image_exists(&image).await.unwrap_or(false) {
+            findings.push(Finding {
+                resource: format!("image {}", image),
+                detail: "not present locally, will be pulled".to_owned(),
+                severity: Severity::Reused,
+            });
+        }
+    }
+
+    findings
+}
+
+pub fn print_report(findings: &[Finding]) {
+    let mut table = Table::new();
+    table.add_row(row!["Resource", "Finding"]);
+
+    if findings.is_empty() {
+        table.add_row(Row::new(vec![
+            Cell::new("-"),
+            Cell::new("nothing pre-existing found").with_style(Attr::ForegroundColor(color::GREEN)),
+        ]));
+    }
+
+    for finding in findings {
+        let row_color = match finding.severity {
+            Severity::Reused => color::YELLOW,
+            Severity::Conflict => color::RED,
+        };
+        table.add_row(Row::new(vec![
+            Cell::new(&finding.resource).with_style(Attr::ForegroundColor(row_color)),
+            Cell::new(&finding.detail).with_style(Attr::ForegroundColor(row_color)),
+        ]));
+    }
+
+    table.printstd();
+}